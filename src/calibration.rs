@@ -0,0 +1,82 @@
+//! calibration.rs
+//! Copyright © 2026 Sean Springer
+//! [This program is licensed under the "MIT License"]
+//! Please see the file LICENSE in the source distribution of this software for license terms.
+//!
+//! Persists a zero-offset calibration (x/y/z mG) to a dedicated NVMC flash page, so the bubble
+//! stays level across resets even when the sensor mount isn't perfectly aligned to the board.
+
+use microbit::pac::NVMC;
+
+/// Identifies a valid calibration record; bump this if the record layout below ever changes so a
+/// stale record from an older layout is ignored rather than misread.
+const MAGIC: u32 = 0xCA1B_0001;
+
+/// Flash page reserved for calibration storage: the last page of the nRF52833's 512KB flash.
+/// `memory.x` carves this address out of the `FLASH` region into its own `CALIBRATION` region, so
+/// the linker refuses to place `.text`/`.rodata` here as the firmware grows, rather than silently
+/// letting a future `erase_page()` call erase live code.
+const PAGE_ADDR: u32 = 0x7_F000;
+
+/// A zero-offset reading, in mG, subtracted from every subsequent accelerometer sample.
+#[derive(Clone, Copy)]
+pub struct Offset {
+    pub x_mg: i32,
+    pub y_mg: i32,
+    pub z_mg: i32,
+}
+
+/// Thin wrapper around the NVMC peripheral for reading and writing the calibration page.
+pub struct Calibration {
+    nvmc: NVMC,
+}
+
+impl Calibration {
+    pub fn new(nvmc: NVMC) -> Self {
+        Calibration { nvmc }
+    }
+
+    /// Read the persisted offset, if the calibration page holds a record whose magic word
+    /// matches. Returns `None` on a blank/erased page or a record from an older layout.
+    pub fn load(&self) -> Option<Offset> {
+        // the page is mapped into the address space even unerased, so a direct volatile read is
+        // safe; flash contents default to all-ones, which will never match MAGIC
+        let words = unsafe { core::slice::from_raw_parts(PAGE_ADDR as *const u32, 4) };
+        if words[0] != MAGIC {
+            return None;
+        }
+
+        Some(Offset {
+            x_mg: words[1] as i32,
+            y_mg: words[2] as i32,
+            z_mg: words[3] as i32,
+        })
+    }
+
+    /// Erase the calibration page and persist `offset` to it.
+    pub fn store(&mut self, offset: Offset) {
+        self.erase_page();
+        self.write_word(PAGE_ADDR, MAGIC);
+        self.write_word(PAGE_ADDR + 4, offset.x_mg as u32);
+        self.write_word(PAGE_ADDR + 8, offset.y_mg as u32);
+        self.write_word(PAGE_ADDR + 12, offset.z_mg as u32);
+    }
+
+    fn erase_page(&mut self) {
+        self.nvmc.config.write(|w| w.wen().een());
+        while self.nvmc.ready.read().ready().is_busy() {}
+        self.nvmc
+            .erasepage()
+            .write(|w| unsafe { w.bits(PAGE_ADDR) });
+        while self.nvmc.ready.read().ready().is_busy() {}
+        self.nvmc.config.write(|w| w.wen().ren());
+    }
+
+    fn write_word(&mut self, address: u32, value: u32) {
+        self.nvmc.config.write(|w| w.wen().wen());
+        while self.nvmc.ready.read().ready().is_busy() {}
+        unsafe { (address as *mut u32).write_volatile(value) };
+        while self.nvmc.ready.read().ready().is_busy() {}
+        self.nvmc.config.write(|w| w.wen().ren());
+    }
+}
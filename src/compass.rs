@@ -0,0 +1,120 @@
+//! compass.rs
+//! Copyright © 2026 Sean Springer
+//! [This program is licensed under the "MIT License"]
+//! Please see the file LICENSE in the source distribution of this software for license terms.
+//!
+//! Tilt-compensated eCompass heading calculation and mapping of that heading onto the 16 LEDs
+//! that make up the perimeter of the MB2 5x5 display.
+
+use libm::atan2f;
+
+/// Number of LEDs around the perimeter of a `LED_SIZE` x `LED_SIZE` grid.
+pub const PERIMETER_LEN: usize = 16;
+
+/// The 16 perimeter LEDs of the 5x5 display, in clockwise order starting at magnetic north
+/// (top-middle LED). Index `i` sits at angle `i * 2π / PERIMETER_LEN` measured clockwise from
+/// north, so a heading can be mapped onto this table with a single division and round.
+const PERIMETER: [(usize, usize); PERIMETER_LEN] = [
+    (0, 2),
+    (0, 3),
+    (0, 4),
+    (1, 4),
+    (2, 4),
+    (3, 4),
+    (4, 4),
+    (4, 3),
+    (4, 2),
+    (4, 1),
+    (4, 0),
+    (3, 0),
+    (2, 0),
+    (1, 0),
+    (0, 0),
+    (0, 1),
+];
+
+/// Tilt-compensate the raw magnetometer reading using the current accelerometer attitude and
+/// return the resulting heading in radians, normalized to `0..2π` where `0` is magnetic north.
+///
+/// `ax`/`ay`/`az` and `mx`/`my`/`mz` are the raw accelerometer and magnetometer axes; only their
+/// relative magnitudes matter, so callers may pass mG/nT readings directly.
+pub fn tilt_compensated_heading(ax: f32, ay: f32, az: f32, mx: f32, my: f32, mz: f32) -> f32 {
+    // roll (phi) and pitch (theta) from the accelerometer
+    let phi = atan2f(ay, az);
+    let theta = atan2f(-ax, ay * libm::sinf(phi) + az * libm::cosf(phi));
+
+    // de-rotate the magnetometer vector into the horizontal plane using roll/pitch
+    let x_h = mx * libm::cosf(theta) + mz * libm::sinf(theta);
+    let y_h = mx * libm::sinf(phi) * libm::sinf(theta) + my * libm::cosf(phi)
+        - mz * libm::sinf(phi) * libm::cosf(theta);
+
+    let heading = atan2f(-y_h, x_h);
+    normalize(heading)
+}
+
+/// Normalize an angle in radians into the range `0..2π`.
+fn normalize(radians: f32) -> f32 {
+    const TAU: f32 = 2.0 * core::f32::consts::PI;
+    let mut angle = radians % TAU;
+    if angle < 0.0 {
+        angle += TAU;
+    }
+    angle
+}
+
+/// Map a heading in radians (`0..2π`, `0` == north) onto the nearest of the 16 perimeter LEDs,
+/// returning its `(row, col)` coordinates into a `LED_SIZE` x `LED_SIZE` grid.
+pub fn nearest_perimeter_led(heading_radians: f32) -> (usize, usize) {
+    const STEP: f32 = 2.0 * core::f32::consts::PI / PERIMETER_LEN as f32;
+    let normalized = normalize(heading_radians);
+    let index = libm::roundf(normalized / STEP) as usize % PERIMETER_LEN;
+    PERIMETER[index]
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+    use core::f32::consts::PI;
+
+    #[test]
+    fn normalize_passes_through_in_range_angles() {
+        assert!((normalize(1.0) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn normalize_wraps_negative_angles_into_0_to_tau() {
+        let normalized = normalize(-1.0);
+        assert!((normalized - (2.0 * PI - 1.0)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn normalize_wraps_angles_past_tau() {
+        let normalized = normalize(2.0 * PI + 0.5);
+        assert!((normalized - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn nearest_perimeter_led_maps_north_to_index_zero() {
+        assert_eq!(nearest_perimeter_led(0.0), PERIMETER[0]);
+    }
+
+    #[test]
+    fn nearest_perimeter_led_maps_south_to_the_opposite_index() {
+        assert_eq!(nearest_perimeter_led(PI), PERIMETER[PERIMETER_LEN / 2]);
+    }
+
+    #[test]
+    fn nearest_perimeter_led_wraps_a_full_turn_back_to_index_zero() {
+        assert_eq!(nearest_perimeter_led(2.0 * PI), PERIMETER[0]);
+    }
+
+    #[test]
+    fn tilt_compensated_heading_matches_flat_device_formula() {
+        // with the device flat (ax = ay = 0, az > 0), roll/pitch are both zero, so the tilt
+        // compensation is a no-op and the heading reduces to atan2(-my, mx) directly
+        let heading = tilt_compensated_heading(0.0, 0.0, 1000.0, 300.0, -400.0, 50.0);
+        let expected = normalize(libm::atan2f(400.0, 300.0));
+        assert!((heading - expected).abs() < 1e-5);
+    }
+}
@@ -8,74 +8,98 @@
 //!
 //! The level will begin in Coarse Mode whereby each LED representes a 250 mG step. Selecting the B
 //! button will switch the level in Fine Mode whereby each LED represents a 25 mG step. Selecting A
-//! will return to Coarse Mode. The LED Refresh rate is fixed at 200ms.
+//! will return to Coarse Mode. Tapping A and B together cycles the mode (Coarse -> Fine -> Compass
+//! -> Coarse); holding them together instead captures a zero-offset calibration. Compass Mode lights
+//! the perimeter LED nearest to magnetic north using a tilt-compensated heading from the
+//! magnetometer. The LED Refresh rate defaults to 200ms, and is independently scheduled from
+//! accelerometer polling.
 //!
-//! Note, GPIOTE Interrupt handlers are used to manage the button press actions with debounce logic
+//! Telemetry and remote control are exposed over the USB-serial UART using the framed protocol
+//! defined in `protocol`; see that module for the wire format.
+//!
+//! Built as an RTIC application: the GPIOTE button events, the button-debounce cooldown,
+//! accelerometer polling (and UART I/O), the speaker/Morse gating, and the display refresh each
+//! run as their own task. The debounce, polling and refresh are scheduled off an RTC monotonic
+//! rather than hand-rolled free-running timers and a busy loop; the speaker/Morse gating keeps the
+//! TIMER2 hardware timer for its finer-grained timing.
 
 #![no_main]
 #![no_std]
 
+mod calibration;
+mod compass;
+mod morse;
+mod protocol;
+mod speaker;
+
 use panic_rtt_target as _;
-use rtt_target::rtt_init_print;
-
-use core::sync::atomic::{
-    AtomicU8,
-    Ordering::{Acquire, Release},
-};
-
-use cortex_m_rt::entry;
-use microbit::{
-    board::Board,
-    display::blocking::Display,
-    hal::{
-        Timer, gpiote,
-        pac::{Interrupt, NVIC, TIMER1, interrupt},
-        twim,
-    },
-    pac::twim0::frequency::FREQUENCY_A,
-};
-
-use critical_section_lock_mut::LockMut;
-use lsm303agr::{AccelMode, AccelOutputDataRate, Lsm303agr};
-
-/// constant refresh rate
-const DISPLAY_REFRESH_RATE_MS: u32 = 200;
+
+/// How often the accelerometer is polled for new data, independent of the display refresh rate.
+const ACCEL_POLL_MS: u64 = 50;
+/// How often the B button is polled for a long-press, independent of the GPIOTE edge task.
+const LONG_PRESS_POLL_MS: u64 = 50;
+/// Number of consecutive `LONG_PRESS_POLL_MS` polls the B button must be held before it is
+/// treated as a long-press requesting the "OK" Morse status.
+const LONG_PRESS_POLL_COUNT: u32 = 15;
+/// Number of consecutive `LONG_PRESS_POLL_MS` polls A+B must be held together before the current
+/// reading is captured as a new zero-offset calibration. Longer than `LONG_PRESS_POLL_COUNT` so a
+/// deliberate calibration hold is clearly distinct from a quick mode-cycling tap.
+const CALIBRATION_HOLD_POLL_COUNT: u32 = 30;
+/// Button debounce cooldown.
+const DEBOUNCE_MS: u64 = 100;
+/// Default display refresh period; overridable at runtime by `HostMessage::SetRefreshMs`.
+const DEFAULT_REFRESH_MS: u32 = 200;
+/// Morse "dot" unit duration, at 1MHz count rate (TIMER2 still free-runs for this fine-grained
+/// audio gating; only the RTIC-scheduled tasks above moved onto the monotonic). A dash is three
+/// units, the gap between elements of a letter is one unit, and the gap between letters is three.
+const MORSE_UNIT_TICKS: u32 = 80 * 1_000_000 / 1000;
+/// Duration of the "level achieved" confirmation beep, in `MORSE_UNIT_TICKS`-sized units.
+const BEEP_UNITS: u8 = 2;
+/// How long (at 1MHz count rate) `accel_poll` waits for each inbound UART byte before giving up
+/// on the rest of the frame. `accel_poll` runs at the same priority as `display_refresh` and the
+/// display's TIMER0 tick, and the read is a byte-at-a-time blocking loop of up to `FRAME_SIZE`
+/// iterations that delays them while it runs, so the per-byte timeout needs to stay well above a byte period at 115200 baud (~87us) while keeping the
+/// worst case (garbage that never produces a COBS delimiter) comfortably under `ACCEL_POLL_MS`.
+const UART_POLL_TICKS: u32 = 300 * 1_000_000 / 1_000_000;
+
 /// MB2 LED grid is 5x5
 const LED_SIZE: usize = 5;
 /// conveience type def
 type LEDState = [[u8; LED_SIZE]; LED_SIZE];
 
-/// 100ms at 1MHz count rate.
-const DEBOUNCE_TIME: u32 = 100 * 1_000_000 / 1000;
-
-/// Global Mutable objects: Used inside interrupt handler
-static RESOLUTION: AtomicU8 = AtomicU8::new(BubbleResolution::Coarse as u8);
-static GPIOTE_PERIPHERAL: LockMut<gpiote::Gpiote> = LockMut::new();
-static DEBOUNCE_TIMER: LockMut<Timer<TIMER1>> = LockMut::new();
+/// Floating-input GPIO pin type shared by the A and B buttons, used to poll the instantaneous
+/// level of a button outside of the GPIOTE edge task (GPIOTE only reports edges, not "is this
+/// also currently held").
+type ButtonPin =
+    microbit::hal::gpio::Pin<microbit::hal::gpio::Input<microbit::hal::gpio::Floating>>;
 
-/// BubbleResolution Enum
+/// Mode Enum
 ///
-/// Used to define the bubble level resolution state
-enum BubbleResolution {
+/// Used to define the bubble level's display mode: the two tilt resolutions plus the
+/// tilt-compensated compass.
+#[derive(Clone, Copy)]
+enum Mode {
     Coarse = 0,
     Fine = 1,
+    Compass = 2,
 }
 
-/// TryFrom<u8> implementation for BubbleResolution enum
+/// TryFrom<u8> implementation for Mode enum
 ///
 /// defines the try_form trait for converting a u8 into either
-/// BubbleResolution::Coarse or BubbleResolution::Fine else returns
+/// Mode::Coarse, Mode::Fine, or Mode::Compass else returns
 /// a unit Error. This implementatoin appears to be the accepted way
 /// to convert a integral type into a C-style Enum.
-impl TryFrom<u8> for BubbleResolution {
+impl TryFrom<u8> for Mode {
     // return Error type is unit bc seems obvious enough
     type Error = ();
 
-    // Returns unit error unless the u8 value is 0 | 1
+    // Returns unit error unless the u8 value is 0 | 1 | 2
     fn try_from(value: u8) -> Result<Self, Self::Error> {
         match value {
-            0 => Ok(BubbleResolution::Coarse),
-            1 => Ok(BubbleResolution::Fine),
+            0 => Ok(Mode::Coarse),
+            1 => Ok(Mode::Fine),
+            2 => Ok(Mode::Compass),
             _ => Err(()),
         }
     }
@@ -84,10 +108,10 @@ impl TryFrom<u8> for BubbleResolution {
 /// LEDs Struct
 ///
 /// Manages the LED state by calculating the proper LED to be lit based upon accelerometer data.
-/// Also retains the current BubbleResolution mode in mode variable.
+/// Also retains the current Mode in mode variable.
 struct LEDs {
     state: LEDState,
-    mode: BubbleResolution,
+    mode: Mode,
 }
 
 /// Impl LEDs
@@ -96,17 +120,19 @@ struct LEDs {
 /// setting the resolution mode, a custom-built round function, and updating the LED state
 /// using the accelerometer x,y,z mG readings
 impl LEDs {
-    /// mG per LED in mode == BubbleResolution::Coarse
+    /// mG per LED in mode == Mode::Coarse
     const COARSE_DIVS: f32 = 250.0;
-    /// mG per LED in mode == BubbleResolution::Fine
+    /// mG per LED in mode == Mode::Fine
     const FINE_DIVS: f32 = 25.0;
+    /// Brightest level a `GreyscaleImage` pixel can take.
+    const MAX_BRIGHTNESS: u8 = 9;
 
     /// Generates a new LEDs struct instance with state set to all zeros (all LEDs off)
-    /// and resolution set to BubbleResolution::Coarse
+    /// and mode set to Mode::Coarse
     fn new() -> Self {
         LEDs {
             state: [[0u8; 5]; 5],
-            mode: BubbleResolution::Coarse,
+            mode: Mode::Coarse,
         }
     }
 
@@ -115,12 +141,12 @@ impl LEDs {
         self.state = [[0u8; 5]; 5];
     }
 
-    /// update the current BubbleResolution mode. Changing the resolution mode will change
-    /// the mG per LED divisions.
+    /// update the current Mode. Changing between Mode::Coarse and Mode::Fine will change
+    /// the mG per LED divisions; Mode::Compass switches the display over to the eCompass.
     ///
-    /// BubbleResolution::Coarse -> LEDs::COARSE_DIVS
-    /// BubbleResolution::Fine -> LEDs::Fine_DIVS
-    fn set_mode(&mut self, mode: BubbleResolution) {
+    /// Mode::Coarse -> LEDs::COARSE_DIVS
+    /// Mode::Fine -> LEDs::FINE_DIVS
+    fn set_mode(&mut self, mode: Mode) {
         self.mode = mode;
     }
 
@@ -136,165 +162,677 @@ impl LEDs {
         integer
     }
 
-    /// Clamp extreme mG magnitudes to the edges of the board. If not outside the range of the level,
-    /// invokes the LEDs::round static method to round the division to the nearest pixel
-    fn clamp(value: f32) -> usize {
-        if value <= 0.0 {
-            0 //clamp off to the left or off the top
+    /// Clamp a fractional pixel coordinate to the board and split it into the two integer cells
+    /// it falls between, plus the fractional weight (`0.0..1.0`) leaning toward the upper cell.
+    /// At the edges of the board both cells coincide and the weight is irrelevant, since the
+    /// (1-frac)+frac split still sums to a single full-brightness contribution.
+    fn bilinear_cells(value: f32) -> (usize, usize, f32) {
+        let clamped = if value <= 0.0 {
+            0.0
         } else if value >= (LED_SIZE - 1usize) as f32 {
-            LED_SIZE - 1 //clamp off to the right or bottom
+            (LED_SIZE - 1usize) as f32
         } else {
-            // cast to usize is safe bc negative values handled above
-            LEDs::round(value) as usize
-        }
+            value
+        };
+
+        let low = clamped as usize; //truncates toward zero, which is floor() for a clamped non-negative value
+        let high = if clamped <= 0.0 {
+            low
+        } else {
+            (low + 1).min(LED_SIZE - 1)
+        };
+        let frac = clamped - low as f32;
+        (low, high, frac)
+    }
+
+    /// Add `weight` worth of `LEDs::MAX_BRIGHTNESS` (rounded) to the cell at `(row, col)`.
+    /// Contributions accumulate, since a clamped edge coordinate can map two bilinear cells onto
+    /// the same one; the sum saturates at `u8::MAX` and is not capped at `MAX_BRIGHTNESS`.
+    fn add_brightness(&mut self, row: usize, col: usize, weight: f32) {
+        let level = LEDs::round(weight * LEDs::MAX_BRIGHTNESS as f32) as u8;
+        self.state[row][col] = self.state[row][col].saturating_add(level);
     }
 
-    /// update and return the LED lit state. If the board is upside-down (z > 0),
-    /// returns a cleared state (all LEDs are off). Otherwise, will return a state with
-    /// one and only one LED in the On (lit) state.
+    /// update and return the LED brightness grid. If the board is upside-down (z > 0), returns a
+    /// cleared grid (all LEDs off). Otherwise, the bubble's fractional pixel position is rendered
+    /// with bilinear weighting across the four surrounding cells, so the bubble visibly slides
+    /// between LEDs instead of snapping to the nearest one.
     ///
     /// Depending upon the resolution mode, transforms the mG accelerometer inputs into a
-    /// pixel value which will be set to the On (lit) state.
+    /// fractional pixel position before spreading its brightness across the grid.
     fn update(&mut self, x: i32, y: i32, z: i32) -> LEDState {
-        if z > 0 {
-            self.clear();
-        } else {
+        self.clear();
+
+        if z <= 0 {
             let divs = match self.mode {
-                BubbleResolution::Coarse => LEDs::COARSE_DIVS,
-                BubbleResolution::Fine => LEDs::FINE_DIVS,
+                Mode::Coarse => LEDs::COARSE_DIVS,
+                Mode::Fine => LEDs::FINE_DIVS,
+                // Compass mode is rendered separately via LEDs::show_heading
+                Mode::Compass => LEDs::COARSE_DIVS,
             };
 
-            self.clear();
-
             let x_pix: f32 = (-x as f32) / divs + 2.0; //needs to be flipped for axis
             let y_pix: f32 = (y as f32) / divs + 2.0;
-            let x_index = LEDs::clamp(y_pix);
-            let y_index = LEDs::clamp(x_pix);
 
-            self.state[x_index][y_index] = 1;
+            // rows come from y_pix and columns from x_pix, same axis mapping as before
+            let (row0, row1, fy) = LEDs::bilinear_cells(y_pix);
+            let (col0, col1, fx) = LEDs::bilinear_cells(x_pix);
+
+            self.add_brightness(row0, col0, (1.0 - fx) * (1.0 - fy));
+            self.add_brightness(row0, col1, fx * (1.0 - fy));
+            self.add_brightness(row1, col0, (1.0 - fx) * fy);
+            self.add_brightness(row1, col1, fx * fy);
         }
 
         self.state
     }
+
+    /// clear the display and light the single perimeter LED nearest to the given
+    /// tilt-compensated heading (in radians, `0` == magnetic north). Used in Mode::Compass.
+    fn show_heading(&mut self, heading_radians: f32) -> LEDState {
+        self.clear();
+        let (row, col) = compass::nearest_perimeter_led(heading_radians);
+        self.state[row][col] = LEDs::MAX_BRIGHTNESS;
+        self.state
+    }
 }
 
-/// GPIOTE Interrupt handler (nrf52833 Peripheral Vector Table Entry #6)
-///
-/// Handles interrupts originating from either the A or B btn press with anti-bouncing logic.
-/// MB2 TIMER1 is used to implement a 100ms cooldown on interrupt handling in order to protect against
-/// button bounce. Then, the sending button is determined by checking which GPIOTE channel triggered the event
-/// (Channel 0 is attached to the A btn and Channel 1 is attached to the B button). The RESOLUTION atomic is
-/// updated using the intergral representation of the appropriate BubblerResolution variant.
-#[interrupt]
-fn GPIOTE() {
-    // check for bouncing using a 100ms timer based coolddown:
-    let mut debounced = false;
-    DEBOUNCE_TIMER.with_lock(|debounce_timer| {
-        if debounce_timer.read() == 0 {
-            debounced = true;
-            debounce_timer.start(DEBOUNCE_TIME);
-        }
-    });
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+
+    #[test]
+    fn round_rounds_half_up() {
+        assert_eq!(LEDs::round(2.4), 2);
+        assert_eq!(LEDs::round(2.5), 3);
+        assert_eq!(LEDs::round(2.6), 3);
+    }
+
+    #[test]
+    fn bilinear_cells_clamps_below_zero() {
+        assert_eq!(LEDs::bilinear_cells(-0.5), (0, 0, 0.0));
+    }
 
-    // grab a mutable reference to the Gpiote instance, determine which button sent the signal,
-    // reset the interrupt, and update the RESOULTION atomic if debounced timer as timed out
-    GPIOTE_PERIPHERAL.with_lock(|gpiote| {
+    #[test]
+    fn bilinear_cells_clamps_above_last_index() {
+        assert_eq!(LEDs::bilinear_cells(4.5), (4, 4, 0.0));
+    }
+
+    #[test]
+    fn bilinear_cells_splits_fractional_position() {
+        let (low, high, frac) = LEDs::bilinear_cells(2.25);
+        assert_eq!((low, high), (2, 3));
+        assert!((frac - 0.25).abs() < 1e-6);
+    }
+
+    #[test]
+    fn add_brightness_rounds_weight_into_max_brightness_units() {
+        let mut leds = LEDs::new();
+        leds.add_brightness(1, 2, 1.0);
+        assert_eq!(leds.state[1][2], LEDs::MAX_BRIGHTNESS);
+    }
+
+    #[test]
+    fn add_brightness_accumulates_overlapping_contributions() {
+        let mut leds = LEDs::new();
+        leds.add_brightness(0, 0, 0.5);
+        leds.add_brightness(0, 0, 0.5);
+        let expected = LEDs::round(0.5 * LEDs::MAX_BRIGHTNESS as f32) as u8 * 2;
+        assert_eq!(leds.state[0][0], expected);
+    }
+
+    #[test]
+    fn update_clears_the_grid_when_upside_down() {
+        let mut leds = LEDs::new();
+        leds.set_mode(Mode::Coarse);
+        let state = leds.update(0, 0, 1);
+        assert!(state.iter().all(|row| row.iter().all(|&cell| cell == 0)));
+    }
+
+    #[test]
+    fn update_lights_the_center_cell_when_level() {
+        let mut leds = LEDs::new();
+        leds.set_mode(Mode::Coarse);
+        let state = leds.update(0, 0, -1000);
+        assert_eq!(state[2][2], LEDs::MAX_BRIGHTNESS);
+    }
+}
+
+#[rtic::app(device = microbit::pac, peripherals = true, dispatchers = [SWI0_EGU0, SWI1_EGU1])]
+mod app {
+    use super::{
+        calibration, compass, morse, speaker, ButtonPin, LEDs, Mode, ACCEL_POLL_MS, BEEP_UNITS,
+        CALIBRATION_HOLD_POLL_COUNT, DEBOUNCE_MS, DEFAULT_REFRESH_MS, LONG_PRESS_POLL_COUNT,
+        LONG_PRESS_POLL_MS, MORSE_UNIT_TICKS, UART_POLL_TICKS,
+    };
+
+    use calibration::{Calibration, Offset};
+    use embedded_hal::digital::v2::InputPin;
+    use lsm303agr::{AccelMode, AccelOutputDataRate, Lsm303agr, MagMode, MagOutputDataRate};
+    use microbit::{
+        board::Board,
+        display::nonblocking::{Display, GreyscaleImage},
+        hal::{
+            gpio,
+            gpiote::Gpiote,
+            pac::{TIMER0, TIMER2, TIMER3, UARTE0},
+            twim, uarte, Timer,
+        },
+        pac::twim0::frequency::FREQUENCY_A,
+    };
+    use rtic_monotonics::nrf::rtc::prelude::*;
+    use speaker::{Speaker, Step, MAX_STEPS};
+
+    use crate::protocol::{self, DeviceMessage, HostMessage};
+
+    rtc_monotonic!(Mono, Rtc0);
+
+    /// The LSM303AGR eCompass driver, addressed over TWIM0 and left in one-shot measurement mode
+    /// (both the accelerometer and magnetometer are polled explicitly by `accel_poll` rather than
+    /// free-running).
+    type Sensor = Lsm303agr<
+        lsm303agr::interface::I2cInterface<twim::Twim<microbit::pac::TWIM0>>,
+        lsm303agr::mode::MagOneShot,
+    >;
+
+    #[shared]
+    struct Shared {
+        mode: Mode,
+        leds: LEDs,
+        refresh_ms: u32,
+        debounce_active: bool,
+        button_pins: (ButtonPin, ButtonPin),
+        speaker: Speaker,
+        morse_timer: Timer<TIMER2>,
+        morse_steps: [Step; MAX_STEPS],
+        morse_len: usize,
+        morse_index: usize,
+        // shared with the TIMER0 row-multiplexing ISR, which ticks it far more often than
+        // `display_refresh` hands it a new `GreyscaleImage`
+        display: Display<TIMER0>,
+        // most recent raw (uncalibrated) accelerometer reading, so `combo_watch` can zero against
+        // whatever `accel_poll` last saw without needing to own the sensor itself
+        last_raw_mg: (i32, i32, i32),
+        calibration_offset: Offset,
+    }
+
+    #[local]
+    struct Local {
+        gpiote: Gpiote,
+        sensor: Sensor,
+        uarte: uarte::Uarte<UARTE0>,
+        uart_timer: Timer<TIMER3>,
+        calibration: Calibration,
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> (Shared, Local) {
+        rtt_target::rtt_init_print!();
+
+        let board = Board::new(cx.device, cx.core);
+
+        let rtc_token = rtic_monotonics::create_rtc0_token!();
+        Mono::start(board.RTC0, rtc_token);
+
+        // TIMER0 drives the non-blocking display's row multiplexing (ticked from its own ISR
+        // below), TIMER3 bounds UART reads.
+        let display = Display::new(board.TIMER0, board.display_pins);
+        let uart_timer = Timer::new(board.TIMER3);
+        // only needed transiently to satisfy the sensor's ODR setup delay; TIMER1 is otherwise
+        // unused now that debounce is scheduled off the RTC monotonic instead of a free-running
+        // timer read
+        let mut init_timer = Timer::new(board.TIMER1);
+
+        // buttons: floating inputs, degraded so they can be parked in a shared resource
+        let a_btn = board.buttons.button_a.into_floating_input().degrade();
+        let b_btn = board.buttons.button_b.into_floating_input().degrade();
+
+        let gpiote = Gpiote::new(board.GPIOTE);
+        let channel0 = gpiote.channel0();
+        let channel1 = gpiote.channel1();
+        channel0.input_pin(&a_btn).hi_to_lo().enable_interrupt();
+        channel0.reset_events();
+        channel1.input_pin(&b_btn).hi_to_lo().enable_interrupt();
+        channel1.reset_events();
+
+        // speaker tone driver (silent until a beep or Morse word is played) and its TIMER2
+        // gating timer, which stays a bare free-running hardware timer since it needs
+        // microsecond-scale re-arming well below the RTC monotonic's tick
+        let speaker_pin = board.speaker_pin.into_push_pull_output(gpio::Level::Low);
+        let speaker = Speaker::new(board.PWM0, speaker_pin.degrade());
+        let mut morse_timer = Timer::new(board.TIMER2);
+        morse_timer.disable_interrupt();
+        morse_timer.reset_event();
+
+        // I2C TWIM0 to the LSM303AGR eCompass (accelerometer + magnetometer)
+        let i2c = twim::Twim::new(board.TWIM0, board.i2c_internal.into(), FREQUENCY_A::K100);
+        let mut sensor = Lsm303agr::new_with_i2c(i2c);
+        sensor.init().unwrap();
+        sensor
+            .set_accel_mode_and_odr(
+                &mut init_timer,
+                AccelMode::HighResolution,
+                AccelOutputDataRate::Hz50,
+            )
+            .unwrap();
+        // the LSM303AGR is also a full eCompass; bring up the magnetometer for Mode::Compass
+        sensor
+            .set_mag_mode_and_odr(
+                &mut init_timer,
+                MagMode::HighResolution,
+                MagOutputDataRate::Hz50,
+            )
+            .unwrap();
+
+        // UARTE0 carries the framed telemetry/command protocol over the micro:bit's USB-serial
+        // interface chip.
+        let uarte = uarte::Uarte::new(
+            board.UARTE0,
+            board.uart.into(),
+            uarte::Parity::EXCLUDED,
+            uarte::Baudrate::BAUD115200,
+        );
+
+        // load a previously persisted zero-offset calibration, if the flash page holds one
+        let calibration = Calibration::new(board.NVMC);
+        let calibration_offset = calibration.load().unwrap_or(Offset {
+            x_mg: 0,
+            y_mg: 0,
+            z_mg: 0,
+        });
+
+        accel_poll::spawn().ok();
+        display_refresh::spawn().ok();
+        button_long_press::spawn().ok();
+        combo_watch::spawn().ok();
+
+        (
+            Shared {
+                mode: Mode::Coarse,
+                leds: LEDs::new(),
+                refresh_ms: DEFAULT_REFRESH_MS,
+                debounce_active: false,
+                button_pins: (a_btn, b_btn),
+                speaker,
+                morse_timer,
+                morse_steps: [Step {
+                    on: false,
+                    units: 0,
+                }; MAX_STEPS],
+                morse_len: 0,
+                morse_index: 0,
+                display,
+                last_raw_mg: (0, 0, 0),
+                calibration_offset,
+            },
+            Local {
+                gpiote,
+                sensor,
+                uarte,
+                uart_timer,
+                calibration,
+            },
+        )
+    }
+
+    /// GPIOTE Interrupt handler (nrf52833 Peripheral Vector Table Entry #6)
+    ///
+    /// Handles interrupts originating from either the A or B btn press. The sending button(s) are
+    /// determined by checking which GPIOTE channel(s) triggered the event (Channel 0 is A, Channel
+    /// 1 is B). A lone A or B press selects Coarse or Fine directly. A combo press is deliberately
+    /// *not* resolved here: whether it ends up cycling the mode or capturing a calibration depends
+    /// on how long A+B end up held together, which only `combo_watch` (an async poller) can judge
+    /// — acting on it immediately here would always fire the mode-cycle before `combo_watch` gets
+    /// a chance to recognize a deliberate calibration hold. Debounce is a `clear_debounce` task
+    /// scheduled `DEBOUNCE_MS` out, rather than a free-running timer read.
+    #[task(binds = GPIOTE, local = [gpiote], shared = [mode, debounce_active, button_pins])]
+    fn gpiote_task(mut cx: gpiote_task::Context) {
+        let gpiote = cx.local.gpiote;
+
+        let mut a_pressed = false;
+        let mut b_pressed = false;
         if gpiote.channel0().is_event_triggered() {
-            //A button press
             gpiote.channel0().reset_events();
-            if debounced {
-                RESOLUTION.store(BubbleResolution::Coarse as u8, Release);
-            }
-        } else if gpiote.channel1().is_event_triggered() {
-            //B button press
+            a_pressed = true;
+        }
+        if gpiote.channel1().is_event_triggered() {
             gpiote.channel1().reset_events();
+            b_pressed = true;
+        }
+        if !(a_pressed || b_pressed) {
+            return;
+        }
 
-            if debounced {
-                RESOLUTION.store(BubbleResolution::Fine as u8, Release);
+        let debounced = cx.shared.debounce_active.lock(|active| {
+            if *active {
+                false
+            } else {
+                *active = true;
+                true
             }
+        });
+        if !debounced {
+            return;
+        }
+        clear_debounce::spawn().ok();
+
+        // poll the instantaneous level of both buttons to detect a combo press, since GPIOTE
+        // only tells us which button(s) triggered this particular edge
+        let combo_held = cx.shared.button_pins.lock(|(a_btn, b_btn)| {
+            a_btn.is_low().unwrap_or(false) && b_btn.is_low().unwrap_or(false)
+        });
+        if combo_held {
+            // leave it to combo_watch to decide, once the hold ends, whether this was a quick
+            // mode-cycling tap or a sustained calibration hold
+            return;
         }
-    });
-}
 
-/// Entry point
-///
-/// Set up the peripherals to be used and initialize the GPIO Events to trigger on either button press
-/// and pass into global Mutex handlers. TIMER 0 is dedicated to the display and TIMER 1 is used to protect
-/// aginst button bounce.
-///
-/// Becuase the LSM303AGR is attached via I2C to the MCU, the TWIM0 peripheral is used to communicate with the
-/// accelerometer within the LSM303AGR.
-#[entry]
-fn main() -> ! {
-    rtt_init_print!();
-
-    let board = Board::take().unwrap();
-
-    // TIMER0 will be dedicated to the LED display
-    let mut display_timer = Timer::new(board.TIMER0);
-    let mut display = Display::new(board.display_pins);
-
-    // ensure buttons are in Floating mode
-    let a_btn = board.buttons.button_a.into_floating_input();
-    let b_btn = board.buttons.button_b.into_floating_input();
-
-    //setup GPIOTE for both button press interrupts
-    let gpiote = gpiote::Gpiote::new(board.GPIOTE);
-    let channel0 = gpiote.channel0();
-    let channel1 = gpiote.channel1();
-    channel0
-        .input_pin(&a_btn.degrade())
-        .hi_to_lo()
-        .enable_interrupt();
-    channel0.reset_events();
-    channel1
-        .input_pin(&b_btn.degrade())
-        .hi_to_lo()
-        .enable_interrupt();
-    channel1.reset_events();
-
-    GPIOTE_PERIPHERAL.init(gpiote);
-
-    //setup debounce timer
-    let mut debounce_timer = Timer::new(board.TIMER1);
-    debounce_timer.disable_interrupt();
-    debounce_timer.reset_event();
-    DEBOUNCE_TIMER.init(debounce_timer);
-
-    // initialize the I2C TWIN0 communication with the accelerometer registers w/in the LSM303AGR
-    let i2c = { twim::Twim::new(board.TWIM0, board.i2c_internal.into(), FREQUENCY_A::K100) };
-    let mut sensor = Lsm303agr::new_with_i2c(i2c);
-    sensor.init().unwrap();
-    sensor
-        .set_accel_mode_and_odr(
-            &mut display_timer,
-            AccelMode::HighResolution,
-            AccelOutputDataRate::Hz50,
-        )
-        .unwrap();
-
-    // new LED state structure
-    let mut leds = LEDs::new();
-
-    // Set up the NVIC to handle interrupts.
-    unsafe { NVIC::unmask(Interrupt::GPIOTE) }; // allow NVIC to handle GPIOTE signals
-    NVIC::unpend(Interrupt::GPIOTE); //clear any currently pending GPIOTE state
-
-    loop {
-        // read state from atomic RESOLUTION, casting to a BubbleResolution enum variant
-        leds.set_mode(BubbleResolution::try_from(RESOLUTION.load(Acquire)).unwrap());
-
-        // in accelerometer data is new, grab the mG normalized X,Y,Z data and pass it
-        // into the LED::update method before rendering
-        if sensor.accel_status().unwrap().xyz_new_data() {
-            let (x, y, z) = sensor.acceleration().unwrap().xyz_mg();
-
-            // send state to LEDs
-            display.show(
-                &mut display_timer,
-                leds.update(x, y, z),
-                DISPLAY_REFRESH_RATE_MS,
-            );
+        cx.shared
+            .mode
+            .lock(|mode| *mode = if a_pressed { Mode::Coarse } else { Mode::Fine });
+    }
+
+    /// Ends the post-press debounce cooldown `DEBOUNCE_MS` after it is spawned, re-arming
+    /// `gpiote_task` to act on the next edge.
+    #[task(shared = [debounce_active])]
+    async fn clear_debounce(mut cx: clear_debounce::Context) {
+        Mono::delay(DEBOUNCE_MS.millis()).await;
+        cx.shared.debounce_active.lock(|active| *active = false);
+    }
+
+    /// Polls the B button for a long-press and keys "OK" in Morse when found, independent of the
+    /// GPIOTE edge task (which only sees presses, not how long one is held). Excludes A also being
+    /// held, since that combination is `combo_watch`'s calibration gesture, not a Morse request.
+    #[task(shared = [button_pins])]
+    async fn button_long_press(mut cx: button_long_press::Context) {
+        let mut held_polls: u32 = 0;
+        loop {
+            Mono::delay(LONG_PRESS_POLL_MS.millis()).await;
+
+            let b_held = cx.shared.button_pins.lock(|(a_btn, b_btn)| {
+                b_btn.is_low().unwrap_or(false) && !a_btn.is_low().unwrap_or(false)
+            });
+
+            if b_held {
+                held_polls += 1;
+                if held_polls == LONG_PRESS_POLL_COUNT {
+                    let mut steps = [Step {
+                        on: false,
+                        units: 0,
+                    }; MAX_STEPS];
+                    let len = speaker::schedule(&[morse::O, morse::K], &mut steps);
+                    start_playback::spawn(steps, len).ok();
+                }
+            } else {
+                held_polls = 0;
+            }
+        }
+    }
+
+    /// Owns the full A+B combo press/hold state machine: spawned once from `init`, it polls
+    /// continuously, and `gpiote_task` never acts on a combo itself. A combo released before `CALIBRATION_HOLD_POLL_COUNT` polls
+    /// is a quick tap and cycles `mode` (Coarse -> Fine -> Compass -> Coarse); a combo held to
+    /// that threshold instead captures `last_raw_mg` as a new zero-offset calibration, persists it
+    /// to flash, and is no longer eligible to also cycle the mode once released.
+    #[task(local = [calibration], shared = [mode, button_pins, last_raw_mg, calibration_offset])]
+    async fn combo_watch(mut cx: combo_watch::Context) {
+        let calibration = cx.local.calibration;
+        let mut held_polls: u32 = 0;
+        let mut calibrated_this_hold = false;
+
+        loop {
+            Mono::delay(LONG_PRESS_POLL_MS.millis()).await;
+
+            let combo_held = cx.shared.button_pins.lock(|(a_btn, b_btn)| {
+                a_btn.is_low().unwrap_or(false) && b_btn.is_low().unwrap_or(false)
+            });
+
+            if combo_held {
+                held_polls += 1;
+                if !calibrated_this_hold && held_polls == CALIBRATION_HOLD_POLL_COUNT {
+                    calibrated_this_hold = true;
+
+                    let (x_mg, y_mg, z_mg) = cx.shared.last_raw_mg.lock(|raw| *raw);
+                    let offset = Offset { x_mg, y_mg, z_mg };
+                    calibration.store(offset);
+                    cx.shared.calibration_offset.lock(|stored| *stored = offset);
+                }
+            } else {
+                if held_polls > 0 && !calibrated_this_hold {
+                    // released before reaching the calibration threshold: treat it as a tap
+                    cx.shared.mode.lock(|mode| {
+                        *mode = match mode {
+                            Mode::Coarse => Mode::Fine,
+                            Mode::Fine => Mode::Compass,
+                            Mode::Compass => Mode::Coarse,
+                        };
+                    });
+                }
+                held_polls = 0;
+                calibrated_this_hold = false;
+            }
+        }
+    }
+
+    /// Begin playback of `steps[..len]` on the speaker: plays the first step immediately and
+    /// hands the rest off to `morse_tick` (TIMER2) so the caller is never blocked waiting for the
+    /// tone to finish. A `len` of zero is a no-op.
+    #[task(shared = [speaker, morse_timer, morse_steps, morse_len, morse_index])]
+    async fn start_playback(mut cx: start_playback::Context, steps: [Step; MAX_STEPS], len: usize) {
+        if len == 0 {
+            return;
+        }
+
+        cx.shared
+            .morse_steps
+            .lock(|dest| dest[..len].copy_from_slice(&steps[..len]));
+        cx.shared.morse_len.lock(|stored_len| *stored_len = len);
+
+        let first = steps[0];
+        cx.shared.speaker.lock(|speaker| {
+            if first.on {
+                speaker.on()
+            } else {
+                speaker.off()
+            }
+        });
+        cx.shared.morse_index.lock(|index| *index = 1);
+
+        cx.shared.morse_timer.lock(|timer| {
+            timer.start(first.units as u32 * MORSE_UNIT_TICKS);
+            timer.enable_interrupt();
+        });
+    }
+
+    /// TIMER2 Interrupt handler (speaker/Morse playback)
+    ///
+    /// Fires once the previous step's tone (or silence) has run its duration. Plays the next
+    /// queued step and re-arms itself for that step's duration; once the schedule is exhausted it
+    /// silences the speaker and disables its own interrupt so it is inert until `start_playback`
+    /// next primes it.
+    #[task(binds = TIMER2, shared = [speaker, morse_timer, morse_steps, morse_len, morse_index])]
+    fn morse_tick(mut cx: morse_tick::Context) {
+        cx.shared.morse_timer.lock(|timer| timer.reset_event());
+
+        let index = cx.shared.morse_index.lock(|index| *index);
+        let len = cx.shared.morse_len.lock(|len| *len);
+
+        if index >= len {
+            cx.shared.speaker.lock(Speaker::off);
+            cx.shared
+                .morse_timer
+                .lock(|timer| timer.disable_interrupt());
+            return;
+        }
+
+        let step = cx.shared.morse_steps.lock(|steps| steps[index]);
+        cx.shared
+            .speaker
+            .lock(|speaker| if step.on { speaker.on() } else { speaker.off() });
+
+        cx.shared
+            .morse_index
+            .lock(|stored_index| *stored_index = index + 1);
+        cx.shared
+            .morse_timer
+            .lock(|timer| timer.start(step.units as u32 * MORSE_UNIT_TICKS));
+    }
+
+    /// Polls the LSM303AGR for fresh readings, applies any inbound `HostMessage` first (per the
+    /// protocol's contract), subtracts the persisted zero-offset calibration, updates the shared
+    /// `leds` grid, triggers the centered-beep, and relays a `DeviceMessage` back over UART.
+    #[task(
+        local = [sensor, uarte, uart_timer],
+        shared = [mode, leds, refresh_ms, last_raw_mg, calibration_offset]
+    )]
+    async fn accel_poll(mut cx: accel_poll::Context) {
+        let sensor = cx.local.sensor;
+        let uarte = cx.local.uarte;
+        let uart_timer = cx.local.uart_timer;
+
+        let mut was_centered = false;
+        let mut one_shot_requested = false;
+
+        loop {
+            Mono::delay(ACCEL_POLL_MS.millis()).await;
+
+            // `read_timeout` only reports success once it fills the whole buffer, and a host
+            // frame is a handful of COBS-encoded bytes, never `FRAME_SIZE` — so read one byte at
+            // a time instead, stopping at the `0x00` COBS frame delimiter or as soon as a byte
+            // fails to arrive within `UART_POLL_TICKS`, then decode whatever prefix came in.
+            let mut rx_frame = [0u8; protocol::FRAME_SIZE];
+            let mut rx_len = 0;
+            while rx_len < rx_frame.len() {
+                match uarte.read_timeout(
+                    &mut rx_frame[rx_len..=rx_len],
+                    uart_timer,
+                    UART_POLL_TICKS,
+                ) {
+                    Ok(()) => {
+                        rx_len += 1;
+                        if rx_frame[rx_len - 1] == 0 {
+                            break;
+                        }
+                    }
+                    Err(_) => break,
+                }
+            }
+            if rx_len > 0 {
+                if let Some(host_message) = protocol::decode(&mut rx_frame[..rx_len]) {
+                    match host_message {
+                        HostMessage::SetMode(value) => {
+                            if let Ok(new_mode) = Mode::try_from(value) {
+                                cx.shared.mode.lock(|mode| *mode = new_mode);
+                            }
+                        }
+                        HostMessage::RequestReading => one_shot_requested = true,
+                        HostMessage::SetRefreshMs(ms) => {
+                            cx.shared.refresh_ms.lock(|refresh| *refresh = ms as u32);
+                        }
+                    }
+                }
+            }
+
+            let mode = cx.shared.mode.lock(|mode| *mode);
+            cx.shared.leds.lock(|leds| leds.set_mode(mode));
+
+            let mut telemetry: Option<(i32, i32, i32, bool)> = None;
+
+            match mode {
+                Mode::Compass => {
+                    // both the accelerometer (for tilt compensation) and magnetometer readings
+                    // must be fresh before a heading can be computed
+                    if sensor.accel_status().unwrap().xyz_new_data()
+                        && sensor.mag_status().unwrap().xyz_new_data()
+                    {
+                        let (ax, ay, az) = sensor.acceleration().unwrap().xyz_mg();
+                        let (mx, my, mz) = sensor.magnetic_field().unwrap().xyz_nt();
+                        cx.shared.last_raw_mg.lock(|raw| *raw = (ax, ay, az));
+
+                        let heading = compass::tilt_compensated_heading(
+                            ax as f32, ay as f32, az as f32, mx as f32, my as f32, mz as f32,
+                        );
+
+                        cx.shared.leds.lock(|leds| leds.show_heading(heading));
+                        telemetry = Some((ax, ay, az, false));
+                    }
+                }
+                Mode::Coarse | Mode::Fine => {
+                    if sensor.accel_status().unwrap().xyz_new_data() {
+                        let (x, y, z) = sensor.acceleration().unwrap().xyz_mg();
+                        cx.shared.last_raw_mg.lock(|raw| *raw = (x, y, z));
+
+                        let offset = cx.shared.calibration_offset.lock(|offset| *offset);
+                        let (x, y, z) = (x - offset.x_mg, y - offset.y_mg, z - offset.z_mg);
+                        let centered = cx.shared.leds.lock(|leds| leds.update(x, y, z)[2][2] != 0);
+
+                        if centered && !was_centered {
+                            let mut steps = [Step {
+                                on: false,
+                                units: 0,
+                            }; MAX_STEPS];
+                            steps[0] = Step {
+                                on: true,
+                                units: BEEP_UNITS,
+                            };
+                            start_playback::spawn(steps, 1).ok();
+                        }
+                        was_centered = centered;
+
+                        telemetry = Some((x, y, z, centered));
+                    }
+                }
+            }
+
+            if one_shot_requested && telemetry.is_none() {
+                let (x, y, z) = sensor.acceleration().unwrap().xyz_mg();
+                let offset = cx.shared.calibration_offset.lock(|offset| *offset);
+                let (x, y, z) = (x - offset.x_mg, y - offset.y_mg, z - offset.z_mg);
+                telemetry = Some((x, y, z, was_centered));
+            }
+            one_shot_requested = false;
+
+            if let Some((x_mg, y_mg, z_mg, centered)) = telemetry {
+                let message = DeviceMessage::Reading {
+                    x_mg,
+                    y_mg,
+                    z_mg,
+                    mode: mode as u8,
+                    centered,
+                };
+                if let Some(frame) = protocol::encode(&message) {
+                    uarte.write(&frame).ok();
+                }
+            }
         }
     }
+
+    /// Hands the current `leds` grid to the non-blocking display as a new `GreyscaleImage`, at
+    /// `refresh_ms` cadence, independent of how often `accel_poll` actually refreshes it. Unlike
+    /// the old blocking `display.show`, this returns immediately; the row multiplexing that
+    /// actually lights the LEDs happens in `display_tick` below.
+    #[task(shared = [leds, refresh_ms, display])]
+    async fn display_refresh(mut cx: display_refresh::Context) {
+        loop {
+            let refresh_ms = cx.shared.refresh_ms.lock(|refresh| *refresh);
+            let state = cx.shared.leds.lock(|leds| leds.state);
+            let image = GreyscaleImage::new(&state);
+            cx.shared.display.lock(|display| display.show(&image));
+
+            Mono::delay((refresh_ms as u64).millis()).await;
+        }
+    }
+
+    /// TIMER0 Interrupt handler (display row multiplexing)
+    ///
+    /// Drives the non-blocking display's internal row-scan state machine; must fire far more
+    /// often than `display_refresh` hands it a new image, which is why the display itself is a
+    /// `#[shared]` resource rather than split across both tasks as `#[local]`.
+    #[task(binds = TIMER0, shared = [display])]
+    fn display_tick(mut cx: display_tick::Context) {
+        cx.shared
+            .display
+            .lock(|display| display.handle_display_event());
+    }
 }
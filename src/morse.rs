@@ -0,0 +1,61 @@
+//! morse.rs
+//! Copyright © 2026 Sean Springer
+//! [This program is licensed under the "MIT License"]
+//! Please see the file LICENSE in the source distribution of this software for license terms.
+//!
+//! A tiny bit-packed Morse code table and decoder, used by `speaker` to key status words like
+//! "OK" out over the onboard buzzer.
+
+/// A single Morse keying element: a short "dot" or a long "dash".
+#[derive(Clone, Copy)]
+pub enum Symbol {
+    Dot,
+    Dash,
+}
+
+/// A letter's Morse code, bit-packed into a single byte: symbols are stored LSB-first (bit 0 is
+/// the first symbol to key, `1` a dash and `0` a dot), with a marker bit set one position past
+/// the last symbol. Because the marker bit is always the highest set bit in the byte, `decode`
+/// recovers the symbol count with `leading_zeros` instead of storing a separate length field.
+pub type MorseCode = u8;
+
+/// "O" — dash dash dash
+pub const O: MorseCode = 0b1111;
+/// "K" — dash dot dash
+pub const K: MorseCode = 0b1101;
+
+/// Decode `code` into its symbols, first-keyed symbol first, writing into `out` and returning
+/// how many of `out`'s entries were written.
+pub fn decode(code: MorseCode, out: &mut [Symbol; 8]) -> usize {
+    let symbol_count = 7 - code.leading_zeros() as usize;
+    for (i, slot) in out.iter_mut().enumerate().take(symbol_count) {
+        *slot = if code & (1 << i) != 0 {
+            Symbol::Dash
+        } else {
+            Symbol::Dot
+        };
+    }
+    symbol_count
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+
+    fn symbols(code: MorseCode) -> std::vec::Vec<bool> {
+        let mut buf = [Symbol::Dot; 8];
+        let n = decode(code, &mut buf);
+        buf[..n].iter().map(|s| matches!(s, Symbol::Dash)).collect()
+    }
+
+    #[test]
+    fn decodes_o_as_three_dashes() {
+        assert_eq!(symbols(O), [true, true, true]);
+    }
+
+    #[test]
+    fn decodes_k_as_dash_dot_dash() {
+        assert_eq!(symbols(K), [true, false, true]);
+    }
+}
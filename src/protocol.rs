@@ -0,0 +1,119 @@
+//! protocol.rs
+//! Copyright © 2026 Sean Springer
+//! [This program is licensed under the "MIT License"]
+//! Please see the file LICENSE in the source distribution of this software for license terms.
+//!
+//! A small, typed, COBS-framed protocol carried over the micro:bit's USB-serial UART, used in
+//! place of ad-hoc RTT prints so a host tool can log telemetry and remotely reconfigure the
+//! level. Frames are `postcard` structures delimited with Consistent Overhead Byte Stuffing, so
+//! either side can resynchronize after a dropped or partial byte without needing a length prefix.
+
+use heapless::Vec;
+use postcard::{from_bytes_cobs, to_vec_cobs};
+use serde::{Deserialize, Serialize};
+
+/// Largest COBS-encoded frame either message enum below can produce. Generous given both are a
+/// handful of scalar fields.
+pub const FRAME_SIZE: usize = 32;
+
+/// Telemetry sent from the device to the host once per `accel_poll` tick (`ACCEL_POLL_MS` in
+/// `main.rs`), independent of the display's own (host-adjustable) `refresh_ms` cadence.
+#[derive(Serialize, Deserialize)]
+pub enum DeviceMessage {
+    /// The most recent accelerometer reading, in mG, alongside the active `Mode` (as its
+    /// integral representation) and whether the bubble is currently dead-centered.
+    Reading {
+        x_mg: i32,
+        y_mg: i32,
+        z_mg: i32,
+        mode: u8,
+        centered: bool,
+    },
+}
+
+/// Commands accepted from the host, applied before the next `LEDs::update`/`show_heading` call.
+#[derive(Serialize, Deserialize)]
+pub enum HostMessage {
+    /// Switch the device to the given `Mode`, by its integral representation.
+    SetMode(u8),
+    /// Ask the device to emit one `DeviceMessage::Reading` immediately, independent of the
+    /// normal once-per-`accel_poll`-tick cadence.
+    RequestReading,
+    /// Change the display refresh period, in milliseconds.
+    SetRefreshMs(u16),
+}
+
+/// COBS-encode `message` into a freshly allocated frame buffer.
+pub fn encode(message: &DeviceMessage) -> Option<Vec<u8, FRAME_SIZE>> {
+    to_vec_cobs(message).ok()
+}
+
+/// Decode a single COBS-framed `HostMessage` out of `frame`, which is mutated in place during
+/// decoding. Returns `None` if `frame` does not hold a valid frame (e.g. it is all zeros because
+/// no host byte has arrived yet).
+pub fn decode(frame: &mut [u8]) -> Option<HostMessage> {
+    from_bytes_cobs(frame).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+
+    fn roundtrip(message: &DeviceMessage) -> DeviceMessage {
+        let mut frame = encode(message).expect("message fits in FRAME_SIZE");
+        from_bytes_cobs(&mut frame).expect("encode output must decode")
+    }
+
+    #[test]
+    fn reading_roundtrips_through_encode_and_decode() {
+        let message = DeviceMessage::Reading {
+            x_mg: -1234,
+            y_mg: 5678,
+            z_mg: 0,
+            mode: 1,
+            centered: true,
+        };
+        match roundtrip(&message) {
+            DeviceMessage::Reading {
+                x_mg,
+                y_mg,
+                z_mg,
+                mode,
+                centered,
+            } => {
+                assert_eq!(
+                    (x_mg, y_mg, z_mg, mode, centered),
+                    (-1234, 5678, 0, 1, true)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn decode_rejects_an_all_zero_frame() {
+        let mut frame = [0u8; FRAME_SIZE];
+        assert!(decode(&mut frame).is_none());
+    }
+
+    #[test]
+    fn host_messages_roundtrip_through_decode() {
+        let mut frame: Vec<u8, FRAME_SIZE> = to_vec_cobs(&HostMessage::SetRefreshMs(250)).unwrap();
+        match decode(&mut frame) {
+            Some(HostMessage::SetRefreshMs(ms)) => assert_eq!(ms, 250),
+            other => panic!("unexpected decode result: {:?}", other.is_some()),
+        }
+    }
+
+    #[test]
+    fn decode_does_not_require_a_full_frame_size_buffer() {
+        // exercises the chunk0-3 fix: accel_poll now hands decode() exactly the bytes that
+        // arrived, not a FRAME_SIZE buffer zero-padded out to the end
+        let mut frame: Vec<u8, FRAME_SIZE> = to_vec_cobs(&HostMessage::RequestReading).unwrap();
+        assert!(frame.len() < FRAME_SIZE);
+        assert!(matches!(
+            decode(&mut frame),
+            Some(HostMessage::RequestReading)
+        ));
+    }
+}
@@ -0,0 +1,101 @@
+//! speaker.rs
+//! Copyright © 2026 Sean Springer
+//! [This program is licensed under the "MIT License"]
+//! Please see the file LICENSE in the source distribution of this software for license terms.
+//!
+//! Non-visual feedback over the micro:bit v2's onboard speaker: a PWM tone generator that can
+//! either chirp a short confirmation beep or key a word out in Morse code. Gating the tone is
+//! driven from the TIMER2 interrupt rather than the main loop, so playback never blocks the
+//! 200ms display refresh.
+
+use microbit::hal::{
+    gpio::{Output, Pin, PushPull},
+    pac::PWM0,
+    pwm::{Channel, Pwm},
+    time::Hertz,
+};
+
+use crate::morse::{self, Symbol};
+
+/// Audible tone frequency: near the middle of typical piezo buzzer response.
+const TONE_HZ: u32 = 1_000;
+
+/// A single step of a fully expanded Morse playback schedule: the speaker is either keyed "on"
+/// or left silent for `units` dot-durations. A dot is 1 unit, a dash is 3 units, the gap between
+/// elements of the same letter is 1 unit, and the gap between letters is 3 units.
+#[derive(Clone, Copy)]
+pub struct Step {
+    pub on: bool,
+    pub units: u8,
+}
+
+/// Largest Morse schedule this driver can expand a word into. "OK" needs 11 steps; this leaves
+/// headroom for longer status words.
+pub const MAX_STEPS: usize = 32;
+
+/// Expand `word` (a sequence of bit-packed `MorseCode`s) into `out` as alternating on/off
+/// `Step`s, returning how many of `out`'s entries were written.
+pub fn schedule(word: &[morse::MorseCode], out: &mut [Step; MAX_STEPS]) -> usize {
+    let mut n = 0;
+    let mut symbol_buf = [Symbol::Dot; 8];
+
+    for (letter_index, &code) in word.iter().enumerate() {
+        let symbol_count = morse::decode(code, &mut symbol_buf);
+
+        for (i, symbol) in symbol_buf[..symbol_count].iter().enumerate() {
+            let units = match symbol {
+                Symbol::Dot => 1,
+                Symbol::Dash => 3,
+            };
+            out[n] = Step { on: true, units };
+            n += 1;
+
+            if i + 1 < symbol_count {
+                out[n] = Step {
+                    on: false,
+                    units: 1,
+                };
+                n += 1;
+            }
+        }
+
+        if letter_index + 1 < word.len() {
+            out[n] = Step {
+                on: false,
+                units: 3,
+            };
+            n += 1;
+        }
+    }
+
+    n
+}
+
+/// Drives the PWM0 peripheral's speaker output: a continuous `TONE_HZ` / 50% duty square wave,
+/// gated on and off by `on`/`off` to produce beeps and Morse code.
+pub struct Speaker {
+    pwm: Pwm<PWM0>,
+}
+
+impl Speaker {
+    /// Configure `pwm` to drive `speaker_pin` with a silent `TONE_HZ` square wave.
+    pub fn new(pwm0: PWM0, speaker_pin: Pin<Output<PushPull>>) -> Self {
+        let pwm = Pwm::new(pwm0);
+        pwm.set_output_pin(Channel::C0, speaker_pin);
+        pwm.set_period(Hertz(TONE_HZ));
+        pwm.set_duty_on_common(pwm.max_duty() / 2);
+        pwm.disable();
+
+        Speaker { pwm }
+    }
+
+    /// Un-gate the tone (speaker audible).
+    pub fn on(&mut self) {
+        self.pwm.enable();
+    }
+
+    /// Gate the tone off (speaker silent).
+    pub fn off(&mut self) {
+        self.pwm.disable();
+    }
+}